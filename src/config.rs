@@ -0,0 +1,258 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::SSOProfile;
+
+/// User-supplied configuration controlling which SSO profiles get generated and what
+/// they're named, loaded from a TOML file passed via `--config`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfileConfig {
+    /// Only profiles matching at least one of these rules are kept. An empty list keeps everything.
+    #[serde(default)]
+    pub include: Vec<FilterRule>,
+
+    /// Profiles matching any of these rules are dropped, even if they matched `include`.
+    #[serde(default)]
+    pub exclude: Vec<FilterRule>,
+
+    /// A template for the generated profile name, e.g. `"{account_name}/{role_name}"`.
+    /// Supports the `{account_name}`, `{account_id}` and `{role_name}` tokens. Defaults
+    /// to `"{account_name}-{role_name}"`, matching the tool's historical naming.
+    pub profile_name_template: Option<String>,
+
+    /// Renames specific accounts by id before the name template is applied, for accounts
+    /// whose SSO-assigned name isn't the one you want in your local profiles.
+    #[serde(default)]
+    pub account_overrides: HashMap<String, String>,
+}
+
+/// A glob rule matched against an SSO profile's account name, account id and/or role
+/// name. Fields left unset are not checked, so a rule only constraining `role_name`
+/// matches that role in every account.
+#[derive(Debug, Deserialize)]
+pub struct FilterRule {
+    pub account_name: Option<String>,
+    pub account_id: Option<String>,
+    pub role_name: Option<String>,
+}
+
+impl FilterRule {
+    fn matches(&self, sso_profile: &SSOProfile) -> Result<bool, anyhow::Error> {
+        let checks = [
+            (&self.account_name, &sso_profile.account_name),
+            (&self.account_id, &sso_profile.account_id),
+            (&self.role_name, &sso_profile.role_name),
+        ];
+
+        for (pattern, value) in checks {
+            if let Some(pattern) = pattern {
+                if !glob::Pattern::new(pattern)?.matches(value) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl ProfileConfig {
+    /// Loads a profile config from a TOML file.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Cannot read config file {}: {}", path.display(), err))?;
+
+        let config: ProfileConfig = toml::from_str(&contents)
+            .map_err(|err| anyhow!("Cannot parse config file {}: {}", path.display(), err))?;
+
+        Ok(config)
+    }
+
+    /// Keeps only the profiles that pass the `include`/`exclude` rules.
+    pub fn filter(&self, sso_profiles: Vec<SSOProfile>) -> Result<Vec<SSOProfile>, anyhow::Error> {
+        let mut filtered = Vec::with_capacity(sso_profiles.len());
+
+        for sso_profile in sso_profiles {
+            if !self.is_included(&sso_profile)? {
+                continue;
+            }
+
+            filtered.push(sso_profile);
+        }
+
+        Ok(filtered)
+    }
+
+    fn is_included(&self, sso_profile: &SSOProfile) -> Result<bool, anyhow::Error> {
+        if !self.include.is_empty() {
+            let mut matched_include = false;
+
+            for rule in &self.include {
+                if rule.matches(sso_profile)? {
+                    matched_include = true;
+                    break;
+                }
+            }
+
+            if !matched_include {
+                return Ok(false);
+            }
+        }
+
+        for rule in &self.exclude {
+            if rule.matches(sso_profile)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Resolves the (unprefixed) profile name for an SSO profile, applying the account
+    /// override and name template.
+    ///
+    /// Tokens are substituted in a single left-to-right scan of the template rather than
+    /// via sequential `.replace()` calls, so a substituted value (e.g. an account override
+    /// containing literal `{role_name}` text) is never re-interpreted as a token.
+    pub fn profile_name(&self, sso_profile: &SSOProfile) -> String {
+        let account_name = self.account_overrides
+            .get(&sso_profile.account_id)
+            .cloned()
+            .unwrap_or_else(|| sso_profile.account_name.clone())
+            .replace(' ', "-");
+
+        let template = self.profile_name_template
+            .as_deref()
+            .unwrap_or("{account_name}-{role_name}");
+
+        let mut result = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(brace_start) = rest.find('{') {
+            result.push_str(&rest[..brace_start]);
+
+            let after_brace = &rest[brace_start + 1..];
+            match after_brace.find('}') {
+                Some(brace_end) => {
+                    match &after_brace[..brace_end] {
+                        "account_name" => result.push_str(&account_name),
+                        "account_id" => result.push_str(&sso_profile.account_id),
+                        "role_name" => result.push_str(&sso_profile.role_name),
+                        other => {
+                            result.push('{');
+                            result.push_str(other);
+                            result.push('}');
+                        }
+                    }
+                    rest = &after_brace[brace_end + 1..];
+                }
+                None => {
+                    result.push_str(&rest[brace_start..]);
+                    rest = "";
+                    break;
+                }
+            }
+        }
+
+        result.push_str(rest);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(account_id: &str, account_name: &str, role_name: &str) -> SSOProfile {
+        SSOProfile {
+            account_id: String::from(account_id),
+            account_name: String::from(account_name),
+            role_name: String::from(role_name),
+            start_url: String::from("https://example.awsapps.com/start"),
+            sso_region: String::from("us-east-1"),
+        }
+    }
+
+    #[test]
+    fn filter_rule_matches_only_constrained_fields() {
+        let rule = FilterRule {
+            account_name: None,
+            account_id: None,
+            role_name: Some(String::from("Admin*")),
+        };
+
+        assert!(rule.matches(&profile("111", "Anything", "AdminAccess")).unwrap());
+        assert!(!rule.matches(&profile("111", "Anything", "ReadOnly")).unwrap());
+    }
+
+    #[test]
+    fn include_rules_require_at_least_one_match() {
+        let config = ProfileConfig {
+            include: vec![FilterRule {
+                account_name: Some(String::from("Prod*")),
+                account_id: None,
+                role_name: None,
+            }],
+            ..ProfileConfig::default()
+        };
+
+        assert!(config.is_included(&profile("111", "Prod-App", "Admin")).unwrap());
+        assert!(!config.is_included(&profile("111", "Dev-App", "Admin")).unwrap());
+    }
+
+    #[test]
+    fn exclude_rules_win_over_include_rules() {
+        let config = ProfileConfig {
+            include: vec![FilterRule {
+                account_name: Some(String::from("*")),
+                account_id: None,
+                role_name: None,
+            }],
+            exclude: vec![FilterRule {
+                account_name: None,
+                account_id: None,
+                role_name: Some(String::from("Billing")),
+            }],
+            ..ProfileConfig::default()
+        };
+
+        assert!(config.is_included(&profile("111", "Prod-App", "Admin")).unwrap());
+        assert!(!config.is_included(&profile("111", "Prod-App", "Billing")).unwrap());
+    }
+
+    #[test]
+    fn profile_name_uses_default_template() {
+        let config = ProfileConfig::default();
+
+        assert_eq!(config.profile_name(&profile("111", "My Account", "Admin")), "My-Account-Admin");
+    }
+
+    #[test]
+    fn profile_name_uses_custom_template() {
+        let config = ProfileConfig {
+            profile_name_template: Some(String::from("{account_id}/{role_name}")),
+            ..ProfileConfig::default()
+        };
+
+        assert_eq!(config.profile_name(&profile("111", "My Account", "Admin")), "111/Admin");
+    }
+
+    #[test]
+    fn profile_name_applies_account_override_without_reinterpreting_its_contents() {
+        let mut account_overrides = HashMap::new();
+        account_overrides.insert(String::from("111"), String::from("literal {role_name} text"));
+
+        let config = ProfileConfig {
+            account_overrides,
+            ..ProfileConfig::default()
+        };
+
+        assert_eq!(
+            config.profile_name(&profile("111", "My Account", "Admin")),
+            "literal-{role_name}-text-Admin"
+        );
+    }
+}
@@ -1,9 +1,124 @@
+mod config;
+
+pub use config::{FilterRule, ProfileConfig};
+
 use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
 use configparser::ini::Ini;
+use futures::future::BoxFuture;
 use futures::StreamExt;
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::path::PathBuf;
 use std::{thread, time};
 
+/// Called with the device flow's verification URL so the caller can get the user to it,
+/// e.g. by opening a browser or printing a message. Defaults to [`default_verification_prompt`].
+pub type VerificationPrompt = Box<dyn Fn(&str) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// The default verification prompt: opens the user's browser and prints the URL as a fallback.
+pub fn default_verification_prompt(verification_uri: &str) -> BoxFuture<'static, ()> {
+    let verification_uri = String::from(verification_uri);
+
+    Box::pin(async move {
+        match open::that(&verification_uri) {
+            _ => {
+                bunt::eprintln!("{$cyan+bold}Open the following link, if it doesn't open automatically, to allow access to SSO:{/$}");
+                eprintln!("{}", verification_uri);
+            }
+        }
+    })
+}
+
+/// A cached SSO access token, matching the format written by the AWS CLI under
+/// `~/.aws/sso/cache/<sha1(start_url)>.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedAccessToken {
+    #[serde(rename = "startUrl")]
+    start_url: String,
+    region: String,
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    #[serde(rename = "expiresAt")]
+    expires_at: DateTime<Utc>,
+}
+
+/// A cached OIDC client registration, so we don't re-register a client every time the
+/// access token expires.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedClientRegistration {
+    #[serde(rename = "clientId")]
+    client_id: String,
+    #[serde(rename = "clientSecret")]
+    client_secret: String,
+    #[serde(rename = "expiresAt")]
+    expires_at: DateTime<Utc>,
+}
+
+/// Returns the directory the AWS CLI keeps its SSO cache files in.
+fn sso_cache_dir() -> Result<PathBuf, anyhow::Error> {
+    let mut path = home::home_dir().ok_or_else(|| anyhow!("Cannot resolve home directory"))?;
+    path.push(".aws");
+    path.push("sso");
+    path.push("cache");
+    Ok(path)
+}
+
+/// Hex-encodes the SHA-1 digest of `input`, matching the AWS CLI's cache file naming.
+fn sha1_hex(input: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(input.as_bytes());
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn access_token_cache_path(start_url: &str) -> Result<PathBuf, anyhow::Error> {
+    let mut path = sso_cache_dir()?;
+    path.push(format!("{}.json", sha1_hex(start_url)));
+    Ok(path)
+}
+
+fn client_registration_cache_path(start_url: &str) -> Result<PathBuf, anyhow::Error> {
+    let mut path = sso_cache_dir()?;
+    path.push(format!("{}-client.json", sha1_hex(start_url)));
+    Ok(path)
+}
+
+/// Writes `value` as JSON to `path`, atomically and (on Unix) with `0600` permissions,
+/// creating any missing parent directories first. On Unix the tmp file is created with
+/// those permissions from the start, so the secrets inside are never briefly
+/// world/group-readable; elsewhere the file gets the platform's default permissions.
+fn write_cache_file<T: Serialize>(path: &PathBuf, value: &T) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let contents = serde_json::to_string_pretty(value)?;
+    let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    fs::write(&tmp_path, contents)?;
+
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct SSOProfile {
     pub account_id: String,
@@ -25,56 +140,237 @@ impl From<&SSOProfile> for IndexMap<String, Option<String>> {
     }
 }
 
+/// Temporary IAM credentials for a single SSO profile, as returned by `GetRoleCredentials`.
+#[derive(Debug)]
+pub struct RoleCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub expiration: String,
+}
+
+impl From<&RoleCredentials> for IndexMap<String, Option<String>> {
+    fn from(role_credentials: &RoleCredentials) -> Self {
+        let mut section: IndexMap<String, Option<String>> = IndexMap::new();
+        section.insert(String::from("aws_access_key_id"), Some(role_credentials.access_key_id.clone()));
+        section.insert(String::from("aws_secret_access_key"), Some(role_credentials.secret_access_key.clone()));
+        section.insert(String::from("aws_session_token"), Some(role_credentials.session_token.clone()));
+        section.insert(String::from("expiration"), Some(role_credentials.expiration.clone()));
+
+        section
+    }
+}
+
 pub struct SSOProfilesLister {
     sso_region: String,
     start_url: String,
+    verification_prompt: VerificationPrompt,
+    non_interactive: bool,
 }
 
 impl SSOProfilesLister {
+    /// Creates a lister with the default verification prompt (open-browser-and-print) and
+    /// interactive mode. Use [`SSOProfilesLister::builder`] to customize either.
     pub fn new(start_url: &str, sso_region: &str) -> Self {
-        SSOProfilesLister {
-            sso_region: String::from(sso_region),
-            start_url: String::from(start_url),
-        }
+        SSOProfilesListerBuilder::new(start_url, sso_region).build()
+    }
+
+    /// Returns a builder for driving the device flow from library code, e.g. to supply a
+    /// custom verification prompt or run non-interactively in CI.
+    pub fn builder(start_url: &str, sso_region: &str) -> SSOProfilesListerBuilder {
+        SSOProfilesListerBuilder::new(start_url, sso_region)
     }
 
     /// Lists the AWS SSO profiles.
     pub async fn list(&self) -> Result<Vec<SSOProfile>, anyhow::Error> {
-        let sdk_config = aws_config::from_env()
+        let (_, sso_profiles) = self.list_with_access_token().await?;
+
+        Ok(sso_profiles)
+    }
+
+    /// Lists the AWS SSO profiles, also returning the access token used to find them so
+    /// it can be reused for follow-up calls such as `get_role_credentials`.
+    pub async fn list_with_access_token(&self) -> Result<(String, Vec<SSOProfile>), anyhow::Error> {
+        let sdk_config = self.sdk_config().await;
+
+        let access_token = match self.cached_access_token()? {
+            Some(access_token) => access_token,
+            None if self.non_interactive => {
+                return Err(anyhow!(
+                    "No valid cached SSO access token, and non-interactive mode won't run the device flow"
+                ))
+            }
+            None => self.device_code_flow(&sdk_config).await?,
+        };
+
+        let sso_profiles = self.list_sso_profiles(&sdk_config, access_token.as_str()).await?;
+
+        Ok((access_token, sso_profiles))
+    }
+
+    /// Resolves temporary IAM credentials for each profile via `GetRoleCredentials`,
+    /// skipping (and warning on) roles that deny access rather than aborting the whole run.
+    pub async fn list_role_credentials<'a>(&self, access_token: &str, sso_profiles: &'a [SSOProfile]) -> Result<Vec<(&'a SSOProfile, RoleCredentials)>, anyhow::Error> {
+        let sdk_config = self.sdk_config().await;
+        let sso_client = aws_sdk_sso::Client::new(&sdk_config);
+
+        let mut role_credentials = Vec::new();
+
+        for sso_profile in sso_profiles {
+            let output = sso_client
+                .get_role_credentials()
+                .access_token(access_token)
+                .account_id(&sso_profile.account_id)
+                .role_name(&sso_profile.role_name)
+                .send()
+                .await;
+
+            let output = match output {
+                Ok(output) => output,
+                Err(aws_sdk_sso::types::SdkError::ServiceError { raw: _, err })
+                    if matches!(err.kind, aws_sdk_sso::error::GetRoleCredentialsErrorKind::AccessDeniedException(_)) =>
+                {
+                    bunt::eprintln!("{$yellow}Skipping{/$} {[white+bold]}: access denied", format!("{}-{}", sso_profile.account_name, sso_profile.role_name));
+                    continue;
+                }
+                Err(err) => return Err(anyhow!(err)),
+            };
+
+            let credentials = output
+                .role_credentials()
+                .ok_or_else(|| anyhow!("GetRoleCredentials provided no credentials"))?;
+
+            role_credentials.push((sso_profile, RoleCredentials {
+                access_key_id: credentials
+                    .access_key_id()
+                    .ok_or_else(|| anyhow!("Role credentials provided no access key id"))?
+                    .to_string(),
+                secret_access_key: credentials
+                    .secret_access_key()
+                    .ok_or_else(|| anyhow!("Role credentials provided no secret access key"))?
+                    .to_string(),
+                session_token: credentials
+                    .session_token()
+                    .ok_or_else(|| anyhow!("Role credentials provided no session token"))?
+                    .to_string(),
+                expiration: credentials.expiration().to_string(),
+            }));
+        }
+
+        Ok(role_credentials)
+    }
+
+    /// Loads the SDK config used to talk to SSO and SSO OIDC.
+    async fn sdk_config(&self) -> aws_config::SdkConfig {
+        aws_config::from_env()
             .region(aws_types::region::Region::new(self.sso_region.clone()))
             .load()
-            .await;
+            .await
+    }
 
-        let access_token = self.device_code_flow(&sdk_config).await?;
-        
-        let sso_profiles = self.list_sso_profiles(&sdk_config, access_token.as_str()).await?;
-        
-        Ok(sso_profiles)
+    /// Loads a still-valid access token from the AWS CLI-compatible SSO token cache.
+    /// Returns `None` on a cache miss, an expired token, or a corrupt cache file.
+    fn cached_access_token(&self) -> Result<Option<String>, anyhow::Error> {
+        let path = access_token_cache_path(&self.start_url)?;
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+
+        let token: CachedAccessToken = match serde_json::from_str(&contents) {
+            Ok(token) => token,
+            Err(_) => return Ok(None),
+        };
+
+        if token.expires_at <= Utc::now() {
+            return Ok(None);
+        }
+
+        Ok(Some(token.access_token))
+    }
+
+    fn cache_access_token(&self, access_token: &str, expires_at: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        let path = access_token_cache_path(&self.start_url)?;
+
+        write_cache_file(&path, &CachedAccessToken {
+            start_url: self.start_url.clone(),
+            region: self.sso_region.clone(),
+            access_token: String::from(access_token),
+            expires_at,
+        })
+    }
+
+    /// Loads a still-valid client registration from the cache, so a new access token
+    /// doesn't force a re-registration. Returns `None` on a cache miss, an expired
+    /// registration, or a corrupt cache file.
+    fn cached_client_registration(&self) -> Result<Option<(String, String)>, anyhow::Error> {
+        let path = client_registration_cache_path(&self.start_url)?;
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+
+        let registration: CachedClientRegistration = match serde_json::from_str(&contents) {
+            Ok(registration) => registration,
+            Err(_) => return Ok(None),
+        };
+
+        if registration.expires_at <= Utc::now() {
+            return Ok(None);
+        }
+
+        Ok(Some((registration.client_id, registration.client_secret)))
+    }
+
+    fn cache_client_registration(&self, client_id: &str, client_secret: &str, expires_at: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        let path = client_registration_cache_path(&self.start_url)?;
+
+        write_cache_file(&path, &CachedClientRegistration {
+            client_id: String::from(client_id),
+            client_secret: String::from(client_secret),
+            expires_at,
+        })
     }
 
     /// Handles AWS SSO's Device Code Flow, returning an access token result.
     async fn device_code_flow(&self, sdk_config: &aws_config::SdkConfig) -> Result<String, anyhow::Error> {
         let sso_client_oidc = aws_sdk_ssooidc::Client::new(sdk_config);
 
-        let register = sso_client_oidc
-            .register_client()
-            .client_name("profile-sync-client")
-            .client_type("public")
-            .scopes("sso-portal:*")
-            .send()
-            .await?;
-
-        let client_id = register
-            .client_id()
-            .ok_or_else(|| anyhow!("SSO Client Registration provided no client id"))?;
-        let client_secret = register
-            .client_secret()
-            .ok_or_else(|| anyhow!("SSO Client Registration provided no client secret"))?;
+        let (client_id, client_secret) = match self.cached_client_registration()? {
+            Some(registration) => registration,
+            None => {
+                let register = sso_client_oidc
+                    .register_client()
+                    .client_name("profile-sync-client")
+                    .client_type("public")
+                    .scopes("sso-portal:*")
+                    .send()
+                    .await?;
+
+                let client_id = register
+                    .client_id()
+                    .ok_or_else(|| anyhow!("SSO Client Registration provided no client id"))?;
+                let client_secret = register
+                    .client_secret()
+                    .ok_or_else(|| anyhow!("SSO Client Registration provided no client secret"))?;
+                let expires_at = DateTime::<Utc>::from_naive_utc_and_offset(
+                    chrono::NaiveDateTime::from_timestamp_opt(register.client_secret_expires_at(), 0)
+                        .ok_or_else(|| anyhow!("SSO Client Registration provided an invalid expiry"))?,
+                    Utc,
+                );
+
+                self.cache_client_registration(client_id, client_secret, expires_at)?;
+
+                (String::from(client_id), String::from(client_secret))
+            }
+        };
 
         let device_authorization = sso_client_oidc
             .start_device_authorization()
-            .client_id(client_id)
-            .client_secret(client_secret)
+            .client_id(&client_id)
+            .client_secret(&client_secret)
             .start_url(&self.start_url)
             .send()
             .await?;
@@ -89,20 +385,15 @@ impl SSOProfilesLister {
             .device_code()
             .ok_or_else(|| anyhow!("SSO Device Authorization provided no device code"))?;
 
-        match open::that(verification_uri) {
-            _ => {
-                bunt::eprintln!("{$cyan+bold}Open the following link, if it doesn't open automatically, to allow access to SSO:{/$}");
-                eprintln!("{}", verification_uri);
-            }
-        }
+        (self.verification_prompt)(verification_uri).await;
 
         let token_output = loop {
             thread::sleep(time::Duration::from_millis(1000));
 
             let res = sso_client_oidc
                 .create_token()
-                .client_id(client_id)
-                .client_secret(client_secret)
+                .client_id(&client_id)
+                .client_secret(&client_secret)
                 .device_code(device_code)
                 .grant_type("urn:ietf:params:oauth:grant-type:device_code")
                 .send()
@@ -123,6 +414,9 @@ impl SSOProfilesLister {
         let access_token = token_output
             .access_token()
             .ok_or_else(|| anyhow!("Token output provided no access token"))?;
+        let expires_at = Utc::now() + Duration::seconds(i64::from(token_output.expires_in()));
+
+        self.cache_access_token(access_token, expires_at)?;
 
         Ok(String::from(access_token))
     }
@@ -181,9 +475,61 @@ impl SSOProfilesLister {
     }
 }
 
+/// Builds an [`SSOProfilesLister`], letting library callers override the verification
+/// prompt and opt into non-interactive mode.
+pub struct SSOProfilesListerBuilder {
+    sso_region: String,
+    start_url: String,
+    verification_prompt: Option<VerificationPrompt>,
+    non_interactive: bool,
+}
+
+impl SSOProfilesListerBuilder {
+    pub fn new(start_url: &str, sso_region: &str) -> Self {
+        SSOProfilesListerBuilder {
+            sso_region: String::from(sso_region),
+            start_url: String::from(start_url),
+            verification_prompt: None,
+            non_interactive: false,
+        }
+    }
+
+    /// Overrides how the device flow's verification URL is surfaced to the user.
+    /// Defaults to [`default_verification_prompt`].
+    pub fn verification_prompt<F>(mut self, verification_prompt: F) -> Self
+    where
+        F: Fn(&str) -> BoxFuture<'static, ()> + Send + Sync + 'static,
+    {
+        self.verification_prompt = Some(Box::new(verification_prompt));
+        self
+    }
+
+    /// When enabled, `list`/`list_with_access_token` error immediately instead of
+    /// running the device flow if no valid cached access token is available.
+    pub fn non_interactive(mut self, non_interactive: bool) -> Self {
+        self.non_interactive = non_interactive;
+        self
+    }
+
+    pub fn build(self) -> SSOProfilesLister {
+        SSOProfilesLister {
+            sso_region: self.sso_region,
+            start_url: self.start_url,
+            verification_prompt: self.verification_prompt.unwrap_or_else(|| Box::new(default_verification_prompt)),
+            non_interactive: self.non_interactive,
+        }
+    }
+}
+
 pub struct AwsConfigMerger {
     pub prefix: String,
     pub clean: bool,
+    pub profile_config: Option<ProfileConfig>,
+
+    /// When set, profiles reference a shared `[sso-session <name>]` block instead of
+    /// repeating `sso_start_url`/`sso_region` in every profile. When unset, the legacy
+    /// per-profile format is written instead.
+    pub sso_session_name: Option<String>,
 }
 
 impl AwsConfigMerger {
@@ -196,32 +542,119 @@ impl AwsConfigMerger {
                 .collect();
 
             let prefix = self.section_name(&self.prefix_name(""));
-            
+
             for key in keys {
                 if key.starts_with(&prefix) {
                     ini_map.remove(&key);
                 }
             }
+
+            if let Some(sso_session_name) = &self.sso_session_name {
+                ini_map.remove(&self.sso_session_section_name(sso_session_name));
+            }
+        }
+
+        if let Some(sso_session_name) = &self.sso_session_name {
+            if let Some(sso_profile) = sso_profiles.first() {
+                let section_name = self.sso_session_section_name(sso_session_name);
+
+                let mut section: IndexMap<String, Option<String>> = IndexMap::new();
+                section.insert(String::from("sso_start_url"), Some(sso_profile.start_url.clone()));
+                section.insert(String::from("sso_region"), Some(sso_profile.sso_region.clone()));
+                section.insert(String::from("sso_registration_scopes"), Some(String::from("sso:account:access")));
+
+                ini_map.insert(section_name, section);
+            }
         }
-        
+
         for sso_profile in sso_profiles {
-            let bare_profile_name = format!("{}-{}", sso_profile.account_name.replace(' ', "-"), &sso_profile.role_name);
-            let profile_name = self.prefix_name(&bare_profile_name);
+            let profile_name = self.prefix_name(&self.bare_profile_name(sso_profile));
             let section_name = self.section_name(&profile_name);
-            
+
             // Resolve conflicts by overwriting with the new profile
             if ini_map.contains_key(&section_name) {
                 ini_map.remove(&section_name);
             }
 
             bunt::eprintln!("{$green}Profile{/$} {[white+bold]}", profile_name);
-    
-            ini_map.insert(String::from(&section_name), sso_profile.into());
+
+            ini_map.insert(String::from(&section_name), self.sso_profile_section(sso_profile));
         }
 
         Ok(())
     }
 
+    /// Populates the `[profile-name]` sections of an AWS credentials file with temporary
+    /// IAM credentials, so tools that don't speak SSO can assume these roles directly.
+    pub fn merge_credentials(&self, role_credentials: &[(&SSOProfile, RoleCredentials)], ini: &mut Ini) -> Result<(), anyhow::Error> {
+        let ini_map = ini.get_mut_map();
+
+        if self.clean {
+            // Unlike merge()'s [profile ...] sections, credentials-file sections are just
+            // the prefixed profile name, so an empty prefix gives no safe, tool-owned marker
+            // to clean by — `key.starts_with("")` would match every section, including
+            // ones this tool never created (e.g. [default]).
+            if self.prefix.is_empty() {
+                return Err(anyhow!(
+                    "--clean requires --prefix when used with --credentials, to avoid removing credentials this tool didn't create"
+                ));
+            }
+
+            let keys: Vec<String> = ini_map.keys()
+                .cloned()
+                .collect();
+
+            let prefix = self.prefix_name("");
+
+            for key in keys {
+                if key.starts_with(&prefix) {
+                    ini_map.remove(&key);
+                }
+            }
+        }
+
+        for (sso_profile, credentials) in role_credentials {
+            let profile_name = self.prefix_name(&self.bare_profile_name(sso_profile));
+
+            // Resolve conflicts by overwriting with the new credentials
+            if ini_map.contains_key(&profile_name) {
+                ini_map.remove(&profile_name);
+            }
+
+            bunt::eprintln!("{$green}Credentials{/$} {[white+bold]}", profile_name);
+
+            ini_map.insert(String::from(&profile_name), credentials.into());
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the unprefixed profile name for an SSO profile, using the configured
+    /// name template when a `ProfileConfig` is set, or the legacy `{account_name}-{role_name}`
+    /// form otherwise.
+    fn bare_profile_name(&self, sso_profile: &SSOProfile) -> String {
+        match &self.profile_config {
+            Some(config) => config.profile_name(sso_profile),
+            None => format!("{}-{}", sso_profile.account_name.replace(' ', "-"), &sso_profile.role_name),
+        }
+    }
+
+    /// Builds a profile's INI section, referencing the shared `[sso-session]` block when
+    /// one is configured, or repeating `sso_start_url`/`sso_region` in the legacy format
+    /// otherwise.
+    fn sso_profile_section(&self, sso_profile: &SSOProfile) -> IndexMap<String, Option<String>> {
+        match &self.sso_session_name {
+            Some(sso_session_name) => {
+                let mut section: IndexMap<String, Option<String>> = IndexMap::new();
+                section.insert(String::from("sso_session"), Some(sso_session_name.clone()));
+                section.insert(String::from("sso_account_id"), Some(sso_profile.account_id.clone()));
+                section.insert(String::from("sso_role_name"), Some(sso_profile.role_name.clone()));
+                section
+            }
+            None => sso_profile.into(),
+        }
+    }
+
     fn prefix_name(&self, profile_name: &str) -> String {
         format!("{}{}", &self.prefix, profile_name)
     }
@@ -229,4 +662,149 @@ impl AwsConfigMerger {
     fn section_name(&self, profile_name: &str) -> String {
         format!("profile {}", profile_name)
     }
+
+    fn sso_session_section_name(&self, sso_session_name: &str) -> String {
+        format!("sso-session {}", sso_session_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `sso_cache_dir` resolves against `$HOME`, so these tests serialize access to it
+    // via this lock and restore the previous value afterwards.
+    static HOME_ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn with_temp_home<T>(f: impl FnOnce() -> T) -> T {
+        let _guard = HOME_ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let previous_home = std::env::var_os("HOME");
+
+        std::env::set_var("HOME", dir.path());
+        let result = f();
+
+        match previous_home {
+            Some(home) => std::env::set_var("HOME", home),
+            None => std::env::remove_var("HOME"),
+        }
+
+        result
+    }
+
+    #[test]
+    fn write_cache_file_round_trips_and_sets_0600_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+
+        let token = CachedAccessToken {
+            start_url: String::from("https://example.awsapps.com/start"),
+            region: String::from("us-east-1"),
+            access_token: String::from("secret-token"),
+            expires_at: Utc::now() + Duration::hours(1),
+        };
+
+        write_cache_file(&path, &token).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let round_tripped: CachedAccessToken = serde_json::from_str(&contents).unwrap();
+        assert_eq!(round_tripped.access_token, "secret-token");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode, 0o600);
+        }
+    }
+
+    #[test]
+    fn write_cache_file_overwrites_an_existing_file_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+
+        let make_token = |access_token: &str| CachedAccessToken {
+            start_url: String::from("https://example.awsapps.com/start"),
+            region: String::from("us-east-1"),
+            access_token: String::from(access_token),
+            expires_at: Utc::now() + Duration::hours(1),
+        };
+
+        write_cache_file(&path, &make_token("old")).unwrap();
+        write_cache_file(&path, &make_token("new")).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let token: CachedAccessToken = serde_json::from_str(&contents).unwrap();
+        assert_eq!(token.access_token, "new");
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn cached_access_token_is_none_on_cache_miss() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+
+            assert_eq!(lister.cached_access_token().unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn cached_access_token_is_none_for_a_corrupt_cache_file() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+            let path = access_token_cache_path(&lister.start_url).unwrap();
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "not valid json").unwrap();
+
+            assert_eq!(lister.cached_access_token().unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn cached_access_token_is_none_once_expired() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+
+            lister.cache_access_token("expired-token", Utc::now() - Duration::seconds(1)).unwrap();
+
+            assert_eq!(lister.cached_access_token().unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn cached_access_token_returns_a_still_valid_token() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+
+            lister.cache_access_token("valid-token", Utc::now() + Duration::hours(1)).unwrap();
+
+            assert_eq!(lister.cached_access_token().unwrap(), Some(String::from("valid-token")));
+        });
+    }
+
+    #[test]
+    fn cached_client_registration_round_trips() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+
+            lister.cache_client_registration("client-id", "client-secret", Utc::now() + Duration::hours(1)).unwrap();
+
+            let (client_id, client_secret) = lister.cached_client_registration().unwrap().unwrap();
+            assert_eq!(client_id, "client-id");
+            assert_eq!(client_secret, "client-secret");
+        });
+    }
+
+    #[test]
+    fn cached_client_registration_is_none_once_expired() {
+        with_temp_home(|| {
+            let lister = SSOProfilesLister::new("https://example.awsapps.com/start", "us-east-1");
+
+            lister.cache_client_registration("client-id", "client-secret", Utc::now() - Duration::seconds(1)).unwrap();
+
+            assert_eq!(lister.cached_client_registration().unwrap(), None);
+        });
+    }
 }
\ No newline at end of file
@@ -24,22 +24,60 @@ struct Args {
     /// Remove old profiles matching the optional prefix
     #[clap(long)]
     clean: bool,
+
+    /// Also populate temporary IAM credentials into ~/.aws/credentials for tools that don't speak SSO
+    #[clap(long)]
+    credentials: bool,
+
+    /// A TOML config file controlling which accounts/roles are generated and how they're named
+    #[clap(long, value_parser)]
+    config: Option<std::path::PathBuf>,
+
+    /// Override the AWS config file to write to (defaults to $AWS_CONFIG_FILE, then ~/.aws/config)
+    #[clap(long, value_parser)]
+    config_file: Option<std::path::PathBuf>,
+
+    /// Write a modern [sso-session] block under this name instead of repeating
+    /// sso_start_url/sso_region in every profile
+    #[clap(long, value_parser)]
+    sso_session_name: Option<String>,
+
+    /// Never fall back to the interactive device flow; fail if no valid cached token is found
+    #[clap(long)]
+    non_interactive: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     let args = Args::parse();
 
+    let profile_config = args.config
+        .as_deref()
+        .map(ProfileConfig::load)
+        .transpose()?;
+
+    let lister = SSOProfilesLister::builder(&args.start_url, &args.sso_region)
+        .non_interactive(args.non_interactive)
+        .build();
+    let (access_token, sso_profiles) = lister.list_with_access_token().await?;
+
+    let sso_profiles = match &profile_config {
+        Some(profile_config) => profile_config.filter(sso_profiles)?,
+        None => sso_profiles,
+    };
+
     let merger = AwsConfigMerger {
         prefix: args.prefix.clone().unwrap_or_else(|| String::from("")),
         clean: args.clean,
+        profile_config,
+        sso_session_name: args.sso_session_name.clone(),
     };
 
     let mut ini = configparser::ini::Ini::new();
 
     if args.populate {
         // Try to locate the AWS config file
-        let aws_config_path = get_aws_config_path().ok_or_else(|| anyhow!("Cannot resolve AWS config file path"))?;
+        let aws_config_path = get_aws_config_path(args.config_file.as_deref()).ok_or_else(|| anyhow!("Cannot resolve AWS config file path"))?;
 
         // Load the AWS Config file
         if aws_config_path.exists() {
@@ -49,33 +87,58 @@ async fn main() -> Result<(), anyhow::Error> {
             };
         }
 
-        let sso_profiles = list_sso_profiles(&args).await?;
-
         merger.merge(&sso_profiles, &mut ini)?;
-        
+
         ini.write(&aws_config_path)?;
     } else {
-        let sso_profiles = list_sso_profiles(&args).await?;
-        
         merger.merge(&sso_profiles, &mut ini)?;
-        
+
         println!("{}", ini.writes());
     }
 
+    if args.credentials {
+        let aws_credentials_path = get_aws_credentials_path().ok_or_else(|| anyhow!("Cannot resolve AWS credentials file path"))?;
+
+        let mut credentials_ini = configparser::ini::Ini::new();
+
+        if aws_credentials_path.exists() {
+            match credentials_ini.load(&aws_credentials_path) {
+                Ok(res) => res,
+                Err(err) => return Err(anyhow!("{}", err)),
+            };
+        }
+
+        let role_credentials = lister.list_role_credentials(&access_token, &sso_profiles).await?;
+
+        merger.merge_credentials(&role_credentials, &mut credentials_ini)?;
+
+        credentials_ini.write(&aws_credentials_path)?;
+    }
+
     Ok(())
 }
 
-async fn list_sso_profiles(args: &Args) -> Result<Vec<SSOProfile>, anyhow::Error> {
-    let lister = SSOProfilesLister::new(&args.start_url, &args.sso_region);
-    let sso_profiles = lister.list().await?;
+/// Resolves the AWS config file to write to: an explicit override first, then
+/// `$AWS_CONFIG_FILE`, then `~/.aws/config`.
+fn get_aws_config_path(override_path: Option<&std::path::Path>) -> Option<std::path::PathBuf> {
+    if let Some(path) = override_path {
+        return Some(path.to_path_buf());
+    }
+
+    if let Ok(path) = std::env::var("AWS_CONFIG_FILE") {
+        return Some(std::path::PathBuf::from(path));
+    }
 
-    Ok(sso_profiles)
+    let mut path = home::home_dir()?;
+    path.push(".aws");
+    path.push("config");
+    Some(path)
 }
 
-fn get_aws_config_path() -> Option<std::path::PathBuf> {
+fn get_aws_credentials_path() -> Option<std::path::PathBuf> {
     if let Some(mut path) = home::home_dir() {
         path.push(".aws");
-        path.push("config");
+        path.push("credentials");
         Some(path)
     } else {
         None